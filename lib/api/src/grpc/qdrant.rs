@@ -439,6 +439,9 @@ pub struct WalConfigDiff {
     #[prost(uint64, optional, tag = "3")]
     #[validate(range(min = 1))]
     pub wal_retain_closed: ::core::option::Option<u64>,
+    /// Compress records of at least this size in bytes with LZ4
+    #[prost(uint64, optional, tag = "4")]
+    pub wal_compression_min_record_size: ::core::option::Option<u64>,
 }
 #[derive(validator::Validate)]
 #[derive(serde::Serialize)]
@@ -16,6 +16,7 @@ use segment::types::{
     VectorStorageDatatype, VectorStorageType,
 };
 use serde::{Deserialize, Serialize};
+use shard::wal::WalCompression;
 use uuid::Uuid;
 use validator::Validate;
 use wal::WalOptions;
@@ -42,6 +43,10 @@ pub struct WalConfig {
     #[validate(range(min = 1))]
     #[serde(default = "default_wal_retain_closed")]
     pub wal_retain_closed: usize,
+    /// Compress WAL records of at least this size in bytes with LZ4.
+    /// If not set - records are stored uncompressed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wal_compression_min_record_size: Option<usize>,
 }
 
 fn default_wal_retain_closed() -> usize {
@@ -54,6 +59,7 @@ impl From<&WalConfig> for WalOptions {
             wal_capacity_mb,
             wal_segments_ahead,
             wal_retain_closed,
+            wal_compression_min_record_size: _,
         } = config;
         WalOptions {
             segment_capacity: wal_capacity_mb * 1024 * 1024,
@@ -63,12 +69,22 @@ impl From<&WalConfig> for WalOptions {
     }
 }
 
+impl From<&WalConfig> for WalCompression {
+    fn from(config: &WalConfig) -> Self {
+        match config.wal_compression_min_record_size {
+            Some(min_record_size) => WalCompression::Lz4 { min_record_size },
+            None => WalCompression::None,
+        }
+    }
+}
+
 impl Default for WalConfig {
     fn default() -> Self {
         WalConfig {
             wal_capacity_mb: 32,
             wal_segments_ahead: 0,
             wal_retain_closed: default_wal_retain_closed(),
+            wal_compression_min_record_size: None,
         }
     }
 }
@@ -103,6 +103,8 @@ pub struct WalConfigDiff {
     pub wal_segments_ahead: Option<usize>,
     /// Number of closed WAL segments to retain
     pub wal_retain_closed: Option<usize>,
+    /// Compress WAL records of at least this size in bytes with LZ4
+    pub wal_compression_min_record_size: Option<usize>,
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Merge, PartialEq, Eq, Hash)]
@@ -283,11 +283,13 @@ impl From<api::grpc::qdrant::WalConfigDiff> for WalConfigDiff {
             wal_capacity_mb,
             wal_segments_ahead,
             wal_retain_closed,
+            wal_compression_min_record_size,
         } = value;
         Self {
             wal_capacity_mb: wal_capacity_mb.map(|v| v as usize),
             wal_segments_ahead: wal_segments_ahead.map(|v| v as usize),
             wal_retain_closed: wal_retain_closed.map(|v| v as usize),
+            wal_compression_min_record_size: wal_compression_min_record_size.map(|v| v as usize),
         }
     }
 }
@@ -516,12 +518,15 @@ impl From<CollectionInfo> for api::grpc::qdrant::CollectionInfo {
                         wal_capacity_mb,
                         wal_segments_ahead,
                         wal_retain_closed,
+                        wal_compression_min_record_size,
                     } = wal_config;
 
                     api::grpc::qdrant::WalConfigDiff {
                         wal_capacity_mb: Some(wal_capacity_mb as u64),
                         wal_segments_ahead: Some(wal_segments_ahead as u64),
                         wal_retain_closed: Some(wal_retain_closed as u64),
+                        wal_compression_min_record_size: wal_compression_min_record_size
+                            .map(|v| v as u64),
                     }
                 }),
                 quantization_config: quantization_config.map(|x| x.into()),
@@ -625,11 +630,13 @@ impl From<api::grpc::qdrant::WalConfigDiff> for WalConfig {
             wal_capacity_mb,
             wal_segments_ahead,
             wal_retain_closed,
+            wal_compression_min_record_size,
         } = wal_config;
         Self {
             wal_capacity_mb: wal_capacity_mb.unwrap_or_default() as usize,
             wal_segments_ahead: wal_segments_ahead.unwrap_or_default() as usize,
             wal_retain_closed: wal_retain_closed.unwrap_or_default() as usize,
+            wal_compression_min_record_size: wal_compression_min_record_size.map(|v| v as usize),
         }
     }
 }
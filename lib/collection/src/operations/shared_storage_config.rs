@@ -3,6 +3,7 @@ use std::num::NonZeroUsize;
 use std::time::Duration;
 
 use segment::types::HnswGlobalConfig;
+use shard::operations::sanitize::OperationLimits;
 
 use crate::common::snapshots_manager::SnapshotsConfig;
 use crate::operations::types::NodeType;
@@ -24,6 +25,10 @@ pub struct SharedStorageConfig {
     pub update_queue_size: usize,
     pub node_type: NodeType,
     pub handle_collection_load_errors: bool,
+    /// Skip WAL records which fail sanity checks on load, instead of failing the shard load
+    pub skip_invalid_wal_records: bool,
+    /// Limits for operations read back from WAL on load
+    pub wal_operation_limits: OperationLimits,
     pub recovery_mode: Option<String>,
     pub search_timeout: Duration,
     pub update_concurrency: Option<NonZeroUsize>,
@@ -43,6 +48,8 @@ impl Default for SharedStorageConfig {
             update_queue_size: DEFAULT_UPDATE_QUEUE_SIZE,
             node_type: Default::default(),
             handle_collection_load_errors: false,
+            skip_invalid_wal_records: false,
+            wal_operation_limits: OperationLimits::default(),
             recovery_mode: None,
             search_timeout: DEFAULT_SEARCH_TIMEOUT,
             update_concurrency: None,
@@ -64,6 +71,8 @@ impl SharedStorageConfig {
         update_queue_size: Option<usize>,
         node_type: NodeType,
         handle_collection_load_errors: bool,
+        skip_invalid_wal_records: bool,
+        wal_operation_limits: OperationLimits,
         recovery_mode: Option<String>,
         search_timeout: Option<Duration>,
         update_concurrency: Option<NonZeroUsize>,
@@ -84,6 +93,8 @@ impl SharedStorageConfig {
             update_queue_size,
             node_type,
             handle_collection_load_errors,
+            skip_invalid_wal_records,
+            wal_operation_limits,
             recovery_mode,
             search_timeout: search_timeout.unwrap_or(DEFAULT_SEARCH_TIMEOUT),
             update_concurrency,
@@ -126,6 +126,26 @@ impl ClockMap {
         (is_accepted, new_tick)
     }
 
+    /// Forget the clock referenced by `clock_tag`.
+    ///
+    /// A recovery point taken afterwards doesn't include the clock, so all of its operations are
+    /// recovered from another replica.
+    pub fn remove_clock(&mut self, clock_tag: ClockTag) {
+        if self.clocks.remove(&Key::from_tag(clock_tag)).is_some() {
+            self.changed = true;
+        }
+    }
+
+    /// Forget all clocks.
+    ///
+    /// A recovery point taken afterwards is empty, so it can't be used for a WAL delta transfer.
+    pub fn clear(&mut self) {
+        if !self.clocks.is_empty() {
+            self.clocks.clear();
+            self.changed = true;
+        }
+    }
+
     /// Create a recovery point based on the current clock map state, so that we can recover any
     /// new operations with new clock values
     ///
@@ -38,6 +38,7 @@ use segment::types::{
     Filter, PayloadIndexInfo, PayloadKeyType, PointIdType, SegmentConfig, SegmentType,
     SnapshotFormat,
 };
+use shard::operations::sanitize::sanitize_operation;
 use shard::wal::SerdeWal;
 use tokio::fs::{create_dir_all, remove_dir_all, remove_file};
 use tokio::runtime::Handle;
@@ -277,7 +278,8 @@ impl LocalShard {
             wal_path.to_str().unwrap(),
             (&collection_config_read.wal_config).into(),
         )
-        .map_err(|e| CollectionError::service_error(format!("Wal error: {e}")))?;
+        .map_err(|e| CollectionError::service_error(format!("Wal error: {e}")))?
+        .with_compression((&collection_config_read.wal_config).into());
 
         // Walk over segments directory and collect all directory entries now
         // Collect now and error early to prevent errors while we've already spawned load threads
@@ -564,7 +566,8 @@ impl LocalShard {
         }
 
         let wal: SerdeWal<OperationWithClockTag> =
-            SerdeWal::new(wal_path.to_str().unwrap(), (&config.wal_config).into())?;
+            SerdeWal::new(wal_path.to_str().unwrap(), (&config.wal_config).into())?
+                .with_compression((&config.wal_config).into());
 
         let optimizers = build_optimizers(
             shard_path,
@@ -642,50 +645,112 @@ impl LocalShard {
         // `SerdeWal` wrapper persists/keeps track of this index (in addition to any handling
         // in the `wal` crate itself).
         //
-        // `SerdeWal::read_all` starts reading WAL from the first "un-truncated" index,
+        // `SerdeWal::try_read_all` starts reading WAL from the first "un-truncated" index,
         // so no additional handling required to "skip" any potentially applied entries.
         //
         // Note, that it's not guaranteed that some operation won't be re-applied to the storage.
-        // (`SerdeWal::read_all` may even start reading WAL from some already truncated
+        // (`SerdeWal::try_read_all` may even start reading WAL from some already truncated
         // index *occasionally*), but the storage can handle it.
 
-        for (op_num, update) in wal.read_all(false) {
-            if let Some(clock_tag) = update.clock_tag {
-                newest_clocks.advance_clock(clock_tag);
-            }
+        // WAL records bypass API validation. Records which can't be decoded, or fail sanity
+        // checks against the collection config, fail the load unless `skip_invalid_wal_records`
+        // is set. See `shard::operations::sanitize` for the details.
+        let segment_config = self
+            .collection_config
+            .read()
+            .await
+            .params
+            .to_segment_config()?;
+        let operation_limits = &self.shared_storage_config.wal_operation_limits;
+
+        // Clocks of skipped records
+        let mut skipped_clocks = Vec::new();
+        let mut skipped_undecodable = false;
+
+        for (op_num, update) in wal.try_read_all(false) {
+            let path = self.path.display();
+
+            let update = match update {
+                Ok(update) => {
+                    let sanitized =
+                        sanitize_operation(&update.operation, &segment_config, operation_limits);
+
+                    match sanitized {
+                        Ok(()) => Ok(update),
+                        // The API writes such operations into the WAL before segments reject
+                        // them. Apply them as usual, so segments reject (and we log) them like
+                        // any other failed operation below.
+                        Err(err) if err.is_vector_mismatch() => Ok(update),
+                        Err(err) => Err((
+                            format!("Invalid WAL operation: {err}"),
+                            Some(update.clock_tag),
+                        )),
+                    }
+                }
+                // The record could not be decoded, so its clock is unknown
+                Err(err) => Err((err.to_string(), None)),
+            };
+
+            match update {
+                Ok(update) => {
+                    if let Some(clock_tag) = update.clock_tag {
+                        newest_clocks.advance_clock(clock_tag);
+                    }
+
+                    // Propagate `CollectionError::ServiceError`, but skip other error types.
+                    match &CollectionUpdater::update(
+                        segments,
+                        op_num,
+                        update.operation,
+                        self.scroll_read_lock.clone(),
+                        self.update_tracker.clone(),
+                        &HardwareCounterCell::disposable(), // Internal operation, no measurement needed.
+                    ) {
+                        Err(err @ CollectionError::ServiceError { error, backtrace }) => {
+                            log::error!(
+                                "Can't apply WAL operation: {error}, \
+                                 collection: {collection_id}, \
+                                 shard: {path}, \
+                                 op_num: {op_num}"
+                            );
+
+                            if let Some(backtrace) = &backtrace {
+                                log::error!("Backtrace: {backtrace}");
+                            }
+
+                            return Err(err.clone());
+                        }
+                        Err(err @ CollectionError::OutOfMemory { .. }) => {
+                            log::error!("{err}");
+                            return Err(err.clone());
+                        }
+                        Err(err @ CollectionError::NotFound { .. }) => log::warn!("{err}"),
+                        Err(err) => log::error!("{err}"),
+                        Ok(_) => (),
+                    }
+                }
+                Err((err, clock_tag)) => {
+                    if !self.shared_storage_config.skip_invalid_wal_records {
+                        return Err(CollectionError::service_error(format!(
+                            "{err}, \
+                             collection: {collection_id}, \
+                             shard: {path}, \
+                             op_num: {op_num}"
+                        )));
+                    }
 
-            // Propagate `CollectionError::ServiceError`, but skip other error types.
-            match &CollectionUpdater::update(
-                segments,
-                op_num,
-                update.operation,
-                self.scroll_read_lock.clone(),
-                self.update_tracker.clone(),
-                &HardwareCounterCell::disposable(), // Internal operation, no measurement needed.
-            ) {
-                Err(err @ CollectionError::ServiceError { error, backtrace }) => {
-                    let path = self.path.display();
-
-                    log::error!(
-                        "Can't apply WAL operation: {error}, \
+                    log::warn!(
+                        "Skipping WAL record: {err}, \
                          collection: {collection_id}, \
                          shard: {path}, \
                          op_num: {op_num}"
                     );
 
-                    if let Some(backtrace) = &backtrace {
-                        log::error!("Backtrace: {backtrace}");
+                    match clock_tag {
+                        Some(clock_tag) => skipped_clocks.extend(clock_tag),
+                        None => skipped_undecodable = true,
                     }
-
-                    return Err(err.clone());
-                }
-                Err(err @ CollectionError::OutOfMemory { .. }) => {
-                    log::error!("{err}");
-                    return Err(err.clone());
                 }
-                Err(err @ CollectionError::NotFound { .. }) => log::warn!("{err}"),
-                Err(err) => log::error!("{err}"),
-                Ok(_) => (),
             }
 
             // Update progress bar or show text progress every WAL_LOAD_REPORT_EVERY
@@ -701,6 +766,18 @@ impl LocalShard {
             }
         }
 
+        // Clocks were already advanced when skipped records were written. Forget them, so the
+        // recovery point of this shard doesn't claim these records, and a WAL delta transfer
+        // resends them from a healthy replica. If a record could not be decoded, we don't know its
+        // clock, so we forget all clocks, which forces a full transfer instead.
+        if skipped_undecodable {
+            newest_clocks.clear();
+        } else {
+            for clock_tag in skipped_clocks {
+                newest_clocks.remove_clock(clock_tag);
+            }
+        }
+
         {
             let segments = self.segments.read();
 
@@ -489,7 +489,11 @@ impl Inner {
             let wal = self.wrapped_shard.wal.wal.lock().await;
             let items_left = (wal.last_index() + 1).saturating_sub(transfer_from);
             let items_total = (transfer_from - self.started_at) + items_left;
-            let batch = wal.read(transfer_from).take(BATCH_SIZE).collect::<Vec<_>>();
+            let batch = wal
+                .try_read(transfer_from)
+                .take(BATCH_SIZE)
+                .map(|(op_num, operation)| operation.map(|operation| (op_num, operation)))
+                .collect::<Result<Vec<_>, _>>()?;
             debug_assert!(
                 batch.len() <= items_left as usize,
                 "batch cannot be larger than items_left",
@@ -751,6 +751,7 @@ mod tests {
             wal_capacity_mb: 1,
             wal_segments_ahead: 0,
             wal_retain_closed: 1,
+            wal_compression_min_record_size: None,
         };
 
         let collection_params = CollectionParams {
@@ -3,11 +3,22 @@ use std::sync::Arc;
 use common::budget::ResourceBudget;
 use common::counter::hardware_accumulator::HwMeasurementAcc;
 use common::save_on_disk::SaveOnDisk;
+use segment::data_types::vectors::VectorStructInternal;
 use segment::types::{PayloadFieldSchema, PayloadSchemaType};
+use shard::operations::sanitize::OperationLimits;
+use shard::wal::SerdeWal;
 use tempfile::Builder;
 use tokio::runtime::Handle;
 use tokio::sync::RwLock;
+use wal::WalOptions;
 
+use crate::operations::point_ops::{
+    BatchPersisted, BatchVectorStructPersisted, PointIdsList, PointInsertOperationsInternal,
+    PointOperations, PointStructPersisted,
+};
+use crate::operations::shared_storage_config::SharedStorageConfig;
+use crate::operations::vector_ops::VectorOperations;
+use crate::operations::{ClockTag, CollectionUpdateOperations, OperationWithClockTag};
 use crate::shards::local_shard::LocalShard;
 use crate::shards::shard_trait::ShardOperation;
 use crate::tests::fixtures::*;
@@ -137,3 +148,385 @@ async fn test_delete_from_indexed_payload() {
     assert_eq!(number_of_indexed_points, 4);
     assert_eq!(number_of_indexed_points_after_load, 3);
 }
+
+/// Append a record with `append_record` after valid operations, and check that it fails the
+/// shard load by default, but is skipped if `skip_invalid_wal_records` is enabled.
+///
+/// Valid operations are tagged with clock 0 of peer 1. Returns the shard loaded with skipping.
+async fn check_skip_invalid_wal_record(append_record: impl FnOnce(&str, WalOptions)) -> LocalShard {
+    let collection_dir = Builder::new().prefix("test_collection").tempdir().unwrap();
+
+    let config = create_collection_config();
+
+    let collection_name = "test".to_string();
+
+    let current_runtime: Handle = Handle::current();
+
+    let payload_index_schema_dir = Builder::new().prefix("qdrant-test").tempdir().unwrap();
+    let payload_index_schema_file = payload_index_schema_dir.path().join("payload-schema.json");
+    let payload_index_schema =
+        Arc::new(SaveOnDisk::load_or_init_default(payload_index_schema_file).unwrap());
+
+    let shard = LocalShard::build(
+        0,
+        collection_name.clone(),
+        collection_dir.path(),
+        Arc::new(RwLock::new(config.clone())),
+        Arc::new(Default::default()),
+        payload_index_schema.clone(),
+        current_runtime.clone(),
+        current_runtime.clone(),
+        ResourceBudget::default(),
+        config.optimizer_config.clone(),
+    )
+    .await
+    .unwrap();
+
+    let operation = OperationWithClockTag::new(upsert_operation(), Some(ClockTag::new(1, 0, 1)));
+    shard
+        .update(operation, true, HwMeasurementAcc::new())
+        .await
+        .unwrap();
+
+    drop(shard);
+
+    append_record(
+        LocalShard::wal_path(collection_dir.path())
+            .to_str()
+            .unwrap(),
+        (&config.wal_config).into(),
+    );
+
+    let load = |shared_storage_config: SharedStorageConfig| {
+        LocalShard::load(
+            0,
+            collection_name.clone(),
+            collection_dir.path(),
+            Arc::new(RwLock::new(config.clone())),
+            config.optimizer_config.clone(),
+            Arc::new(shared_storage_config),
+            payload_index_schema.clone(),
+            true,
+            current_runtime.clone(),
+            current_runtime.clone(),
+            ResourceBudget::default(),
+        )
+    };
+
+    // By default an invalid record fails the load
+    assert!(load(SharedStorageConfig::default()).await.is_err());
+
+    // With skipping enabled the record is dropped, and valid records are still applied
+    let shard = load(SharedStorageConfig {
+        skip_invalid_wal_records: true,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let info = shard.info().await.unwrap();
+    assert_eq!(info.points_count, 5);
+
+    shard
+}
+
+/// Clock ids in the recovery point of `shard`
+async fn recovery_point_clocks(shard: &LocalShard) -> Vec<u32> {
+    let mut clocks: Vec<_> = shard
+        .recovery_point()
+        .await
+        .iter_as_clock_tags()
+        .map(|clock_tag| clock_tag.clock_id)
+        .collect();
+    clocks.sort_unstable();
+    clocks
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_skip_invalid_wal_records() {
+    // A malformed operation, which the API would never accept
+    let shard = check_skip_invalid_wal_record(|path, options| {
+        let mut wal: SerdeWal<OperationWithClockTag> = SerdeWal::new(path, options).unwrap();
+
+        let batch = BatchPersisted {
+            ids: vec![100.into(), 101.into()],
+            vectors: BatchVectorStructPersisted::Single(vec![vec![1.0, 2.0, 3.0, 4.0]]),
+            payloads: None,
+        };
+        let operation = CollectionUpdateOperations::PointOperation(PointOperations::UpsertPoints(
+            PointInsertOperationsInternal::from(batch),
+        ));
+
+        wal.write(&OperationWithClockTag::new(
+            operation,
+            Some(ClockTag::new(1, 1, 1)),
+        ))
+        .unwrap();
+        wal.flush().unwrap();
+    })
+    .await;
+
+    // The clock of the skipped record is not part of the recovery point, so it is recovered
+    // from another replica
+    assert_eq!(recovery_point_clocks(&shard).await, vec![0]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_skip_undecodable_wal_records() {
+    // A record of another type, which can't be decoded as an operation
+    let shard = check_skip_invalid_wal_record(|path, options| {
+        let mut wal: SerdeWal<String> = SerdeWal::new(path, options).unwrap();
+
+        wal.write(&"not an operation".to_string()).unwrap();
+        wal.flush().unwrap();
+    })
+    .await;
+
+    // The clock of an undecodable record is unknown, so the whole shard must be recovered
+    assert!(recovery_point_clocks(&shard).await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_load_wal_with_rejected_vectors() {
+    let collection_dir = Builder::new().prefix("test_collection").tempdir().unwrap();
+
+    let config = create_collection_config();
+
+    let collection_name = "test".to_string();
+
+    let current_runtime: Handle = Handle::current();
+
+    let payload_index_schema_dir = Builder::new().prefix("qdrant-test").tempdir().unwrap();
+    let payload_index_schema_file = payload_index_schema_dir.path().join("payload-schema.json");
+    let payload_index_schema =
+        Arc::new(SaveOnDisk::load_or_init_default(payload_index_schema_file).unwrap());
+
+    let shard = LocalShard::build(
+        0,
+        collection_name.clone(),
+        collection_dir.path(),
+        Arc::new(RwLock::new(config.clone())),
+        Arc::new(Default::default()),
+        payload_index_schema.clone(),
+        current_runtime.clone(),
+        current_runtime.clone(),
+        ResourceBudget::default(),
+        config.optimizer_config.clone(),
+    )
+    .await
+    .unwrap();
+
+    shard
+        .update(upsert_operation().into(), true, HwMeasurementAcc::new())
+        .await
+        .unwrap();
+
+    // Requests which don't match collection vectors are written into the WAL,
+    // before segments reject them
+    let wrong_dimension = PointStructPersisted {
+        id: 100.into(),
+        vector: VectorStructInternal::from(vec![1.0, 2.0, 3.0, 4.0, 5.0]).into(),
+        payload: None,
+    };
+    let operation = CollectionUpdateOperations::PointOperation(PointOperations::UpsertPoints(
+        PointInsertOperationsInternal::from(vec![wrong_dimension]),
+    ));
+    let result = shard
+        .update(operation.into(), true, HwMeasurementAcc::new())
+        .await;
+    assert!(result.is_err());
+
+    let operation = CollectionUpdateOperations::VectorOperation(VectorOperations::DeleteVectors(
+        PointIdsList::from(vec![1.into()]),
+        vec!["unknown".to_string()],
+    ));
+    let result = shard
+        .update(operation.into(), true, HwMeasurementAcc::new())
+        .await;
+    assert!(result.is_err());
+
+    drop(shard);
+
+    // Rejected operations must not prevent the shard from loading with the default config
+    let shard = LocalShard::load(
+        0,
+        collection_name.clone(),
+        collection_dir.path(),
+        Arc::new(RwLock::new(config.clone())),
+        config.optimizer_config.clone(),
+        Arc::new(SharedStorageConfig::default()),
+        payload_index_schema.clone(),
+        true,
+        current_runtime.clone(),
+        current_runtime.clone(),
+        ResourceBudget::default(),
+    )
+    .await
+    .unwrap();
+
+    let info = shard.info().await.unwrap();
+    assert_eq!(info.points_count, 5);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_wal_operation_limits() {
+    let collection_dir = Builder::new().prefix("test_collection").tempdir().unwrap();
+
+    let config = create_collection_config();
+
+    let collection_name = "test".to_string();
+
+    let current_runtime: Handle = Handle::current();
+
+    let payload_index_schema_dir = Builder::new().prefix("qdrant-test").tempdir().unwrap();
+    let payload_index_schema_file = payload_index_schema_dir.path().join("payload-schema.json");
+    let payload_index_schema =
+        Arc::new(SaveOnDisk::load_or_init_default(payload_index_schema_file).unwrap());
+
+    let shard = LocalShard::build(
+        0,
+        collection_name.clone(),
+        collection_dir.path(),
+        Arc::new(RwLock::new(config.clone())),
+        Arc::new(Default::default()),
+        payload_index_schema.clone(),
+        current_runtime.clone(),
+        current_runtime.clone(),
+        ResourceBudget::default(),
+        config.optimizer_config.clone(),
+    )
+    .await
+    .unwrap();
+
+    shard
+        .update(upsert_operation().into(), true, HwMeasurementAcc::new())
+        .await
+        .unwrap();
+
+    drop(shard);
+
+    let load = |shared_storage_config: SharedStorageConfig| {
+        LocalShard::load(
+            0,
+            collection_name.clone(),
+            collection_dir.path(),
+            Arc::new(RwLock::new(config.clone())),
+            config.optimizer_config.clone(),
+            Arc::new(shared_storage_config),
+            payload_index_schema.clone(),
+            true,
+            current_runtime.clone(),
+            current_runtime.clone(),
+            ResourceBudget::default(),
+        )
+    };
+
+    // The upsert of 5 points exceeds the configured batch size limit
+    let wal_operation_limits = OperationLimits {
+        max_batch_size: 4,
+        ..Default::default()
+    };
+
+    assert!(
+        load(SharedStorageConfig {
+            wal_operation_limits,
+            ..Default::default()
+        })
+        .await
+        .is_err(),
+    );
+
+    let shard = load(SharedStorageConfig {
+        skip_invalid_wal_records: true,
+        wal_operation_limits,
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let info = shard.info().await.unwrap();
+    assert_eq!(info.points_count, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_load_compressed_wal() {
+    let collection_dir = Builder::new().prefix("test_collection").tempdir().unwrap();
+
+    let mut config = create_collection_config();
+    config.wal_config.wal_compression_min_record_size = Some(0);
+
+    let collection_name = "test".to_string();
+
+    let current_runtime: Handle = Handle::current();
+
+    let payload_index_schema_dir = Builder::new().prefix("qdrant-test").tempdir().unwrap();
+    let payload_index_schema_file = payload_index_schema_dir.path().join("payload-schema.json");
+    let payload_index_schema =
+        Arc::new(SaveOnDisk::load_or_init_default(payload_index_schema_file).unwrap());
+
+    let shard = LocalShard::build(
+        0,
+        collection_name.clone(),
+        collection_dir.path(),
+        Arc::new(RwLock::new(config.clone())),
+        Arc::new(Default::default()),
+        payload_index_schema.clone(),
+        current_runtime.clone(),
+        current_runtime.clone(),
+        ResourceBudget::default(),
+        config.optimizer_config.clone(),
+    )
+    .await
+    .unwrap();
+
+    let points = (0..10u64)
+        .map(|i| PointStructPersisted {
+            id: i.into(),
+            vector: VectorStructInternal::from(vec![i as f32, 1.0, 2.0, 3.0]).into(),
+            payload: Some(
+                serde_json::from_value(serde_json::json!({
+                    "title": format!("document {i}"),
+                    "text": "some long text, which is repeated over and over ".repeat(20),
+                }))
+                .unwrap(),
+            ),
+        })
+        .collect::<Vec<_>>();
+    let operation = OperationWithClockTag::from(CollectionUpdateOperations::PointOperation(
+        PointOperations::UpsertPoints(PointInsertOperationsInternal::from(points)),
+    ));
+
+    shard
+        .update(operation.clone(), true, HwMeasurementAcc::new())
+        .await
+        .unwrap();
+
+    drop(shard);
+
+    // The record is stored compressed
+    let wal_options: WalOptions = (&config.wal_config).into();
+    let wal =
+        wal::Wal::with_options(LocalShard::wal_path(collection_dir.path()), &wal_options).unwrap();
+    let record = wal.entry(wal.first_index()).unwrap();
+    assert!(record.len() < serde_cbor::to_vec(&operation).unwrap().len());
+    drop(wal);
+
+    let shard = LocalShard::load(
+        0,
+        collection_name.clone(),
+        collection_dir.path(),
+        Arc::new(RwLock::new(config.clone())),
+        config.optimizer_config.clone(),
+        Arc::new(SharedStorageConfig::default()),
+        payload_index_schema.clone(),
+        true,
+        current_runtime.clone(),
+        current_runtime.clone(),
+        ResourceBudget::default(),
+    )
+    .await
+    .unwrap();
+
+    let info = shard.info().await.unwrap();
+    assert_eq!(info.points_count, 10);
+}
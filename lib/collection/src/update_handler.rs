@@ -274,7 +274,8 @@ impl UpdateHandler {
             None => {}
             Some(first_failed_op) => {
                 let wal_lock = wal.lock().await;
-                for (op_num, operation) in wal_lock.read(first_failed_op) {
+                for (op_num, operation) in wal_lock.try_read(first_failed_op) {
+                    let operation = operation?;
                     CollectionUpdater::update(
                         &segments,
                         op_num,
@@ -110,8 +110,8 @@ impl RecoverableWal {
             self.wal
                 .lock()
                 .await
-                .read_all(true)
-                .map(|(op_num, op)| (op_num, op.clock_tag)),
+                .try_read_all(true)
+                .map(|(op_num, op)| (op_num, op.map(|op| op.clock_tag))),
             recovery_point,
             newest_clocks,
             oldest_clocks,
@@ -160,7 +160,7 @@ impl RecoverableWal {
 /// If `None` - the remote WAL is already equal, and we don't have to send any records.
 /// If `Err` - no delta can be resolved.
 fn resolve_wal_delta(
-    operations: impl DoubleEndedIterator<Item = (u64, Option<ClockTag>)>,
+    operations: impl DoubleEndedIterator<Item = (u64, shard::wal::Result<Option<ClockTag>>)>,
     mut recovery_point: RecoveryPoint,
     mut newest_clocks: RecoveryPoint,
     mut oldest_clocks: RecoveryPoint,
@@ -213,6 +213,12 @@ fn resolve_wal_delta(
     let mut last_op_num = None;
 
     for (op_num, clock_tag) in operations.rev() {
+        // We cannot resolve a delta over records we can't read
+        let clock_tag = clock_tag.map_err(|err| WalDeltaError::InvalidRecord {
+            op_num,
+            error: err.to_string(),
+        })?;
+
         // We cannot resolve a delta if we have untagged records
         let Some(clock_tag) = clock_tag else {
             return Err(WalDeltaError::UntaggedRecords);
@@ -252,6 +258,8 @@ pub enum WalDeltaError {
     Cutoff,
     #[error("WAL delta cannot include records without clock tags")]
     UntaggedRecords,
+    #[error("WAL delta cannot include record {op_num}, which can't be read: {error}")]
+    InvalidRecord { op_num: u64, error: String },
     #[error("cannot find slice of WAL records that satisfies the recovery point")]
     NotFound,
 }
@@ -1491,7 +1499,7 @@ mod tests {
             wal.wal
                 .blocking_lock()
                 .read_all(true)
-                .map(|(op_num, op)| (op_num, op.clock_tag)),
+                .map(|(op_num, op)| (op_num, Ok(op.clock_tag))),
             recovery_point,
             newest_clocks,
             RecoveryPoint::default(),
@@ -1518,7 +1526,7 @@ mod tests {
             wal.wal
                 .blocking_lock()
                 .read_all(true)
-                .map(|(op_num, op)| (op_num, op.clock_tag)),
+                .map(|(op_num, op)| (op_num, Ok(op.clock_tag))),
             recovery_point,
             newest_clocks,
             RecoveryPoint::default(),
@@ -1526,6 +1534,38 @@ mod tests {
         assert_eq!(resolve_result.unwrap_err(), WalDeltaError::UnknownClocks);
     }
 
+    /// A record that can't be read cannot be part of a diff.
+    #[test]
+    fn test_recover_point_over_invalid_record() {
+        let mut recovery_point = RecoveryPoint::default();
+        let mut newest_clocks = RecoveryPoint::default();
+
+        recovery_point.insert(1, 0, 0);
+        newest_clocks.insert(1, 0, 2);
+
+        let operations = vec![
+            (0, Ok(Some(ClockTag::new(1, 0, 1)))),
+            (
+                1,
+                Err(shard::wal::WalError::DecodeWalError(
+                    "corrupted".to_string(),
+                )),
+            ),
+            (2, Ok(Some(ClockTag::new(1, 0, 2)))),
+        ];
+
+        let resolve_result = resolve_wal_delta(
+            operations.into_iter(),
+            recovery_point,
+            newest_clocks,
+            RecoveryPoint::default(),
+        );
+        assert!(matches!(
+            resolve_result.unwrap_err(),
+            WalDeltaError::InvalidRecord { op_num: 1, .. },
+        ));
+    }
+
     /// Recovery point with higher clocks than the source cannot resolve a diff.
     #[test]
     fn test_recover_point_higher_than_source() {
@@ -1544,7 +1584,7 @@ mod tests {
             wal.wal
                 .blocking_lock()
                 .read_all(true)
-                .map(|(op_num, op)| (op_num, op.clock_tag)),
+                .map(|(op_num, op)| (op_num, Ok(op.clock_tag))),
             recovery_point,
             newest_clocks,
             RecoveryPoint::default(),
@@ -1575,7 +1615,7 @@ mod tests {
             wal.wal
                 .blocking_lock()
                 .read_all(true)
-                .map(|(op_num, op)| (op_num, op.clock_tag)),
+                .map(|(op_num, op)| (op_num, Ok(op.clock_tag))),
             recovery_point,
             newest_clocks,
             oldest_clocks,
@@ -1601,7 +1641,7 @@ mod tests {
             wal.wal
                 .blocking_lock()
                 .read_all(true)
-                .map(|(op_num, op)| (op_num, op.clock_tag)),
+                .map(|(op_num, op)| (op_num, Ok(op.clock_tag))),
             recovery_point,
             newest_clocks,
             RecoveryPoint::default(),
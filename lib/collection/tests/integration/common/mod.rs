@@ -39,6 +39,7 @@ pub async fn simple_collection_fixture(collection_path: &Path, shard_number: u32
         wal_capacity_mb: 1,
         wal_segments_ahead: 0,
         wal_retain_closed: 1,
+        wal_compression_min_record_size: None,
     };
 
     let collection_params = CollectionParams {
@@ -39,6 +39,7 @@ pub async fn multi_vec_collection_fixture(collection_path: &Path, shard_number:
         wal_capacity_mb: 1,
         wal_segments_ahead: 0,
         wal_retain_closed: 1,
+        wal_compression_min_record_size: None,
     };
 
     let vector_params1 = VectorParamsBuilder::new(4, Distance::Dot).build();
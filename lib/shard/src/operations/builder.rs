@@ -0,0 +1,494 @@
+//! Fluent construction of [`CollectionUpdateOperations`].
+//!
+//! Building operations by hand requires picking the right nesting of point, payload, vector and
+//! field index operation types. [`UpdateBuilder`] hides that nesting behind one method per
+//! action, and checks the resulting operations in [`UpdateBuilder::build`] before they are
+//! handed to a shard.
+
+use std::collections::BTreeSet;
+
+use segment::data_types::vectors::DEFAULT_VECTOR_NAME;
+use segment::json_path::JsonPath;
+use segment::types::{
+    Filter, Payload, PayloadFieldSchema, PayloadKeyType, PointIdType, VectorName, VectorNameBuf,
+};
+use thiserror::Error;
+
+use super::payload_ops::{DeletePayloadOp, PayloadOps, SetPayloadOp};
+use super::point_ops::{
+    PointIdsList, PointInsertOperationsInternal, PointOperations, PointStructPersisted,
+    VectorStructPersisted,
+};
+use super::vector_ops::{PointVectorsPersisted, UpdateVectorsOp, VectorOperations};
+use super::{CollectionUpdateOperations, CreateIndex, FieldIndexOperations};
+
+/// Error returned by [`UpdateBuilder::build`]. `index` is the position of the offending operation.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UpdateBuilderError {
+    #[error("operation {index} has no {what}")]
+    EmptyBatch { index: usize, what: &'static str },
+    #[error(
+        "operation {index}: point {point} has vectors {got:?}, but other points have {expected:?}"
+    )]
+    InconsistentVectorNames {
+        index: usize,
+        point: PointIdType,
+        expected: Vec<VectorNameBuf>,
+        got: Vec<VectorNameBuf>,
+    },
+}
+
+/// Builder for a sequence of [`CollectionUpdateOperations`].
+///
+/// Operations are produced in the order the builder methods are called. Consecutive upserts of
+/// points with the same vector names are merged into a single operation.
+#[derive(Debug, Clone, Default)]
+pub struct UpdateBuilder {
+    operations: Vec<CollectionUpdateOperations>,
+}
+
+impl UpdateBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert or update a single point
+    pub fn upsert(self, point: PointStructPersisted) -> Self {
+        self.upsert_points([point])
+    }
+
+    /// Insert or update points
+    pub fn upsert_points(mut self, points: impl IntoIterator<Item = PointStructPersisted>) -> Self {
+        let points: Vec<_> = points.into_iter().collect();
+
+        if let Some(CollectionUpdateOperations::PointOperation(PointOperations::UpsertPoints(
+            PointInsertOperationsInternal::PointsList(list),
+        ))) = self.operations.last_mut()
+            && same_vector_names(list, &points)
+        {
+            list.extend(points);
+            return self;
+        }
+
+        self.push(CollectionUpdateOperations::PointOperation(
+            PointOperations::UpsertPoints(PointInsertOperationsInternal::PointsList(points)),
+        ))
+    }
+
+    /// Delete points by ids
+    pub fn delete(self, ids: Vec<PointIdType>) -> Self {
+        self.push(CollectionUpdateOperations::PointOperation(
+            PointOperations::DeletePoints { ids },
+        ))
+    }
+
+    /// Delete points matching `filter`
+    pub fn delete_by_filter(self, filter: Filter) -> Self {
+        self.push(CollectionUpdateOperations::PointOperation(
+            PointOperations::DeletePointsByFilter(filter),
+        ))
+    }
+
+    /// Merge `payload` into the payload of the given points
+    pub fn set_payload(self, ids: Vec<PointIdType>, payload: Payload) -> Self {
+        self.push(CollectionUpdateOperations::PayloadOperation(
+            PayloadOps::SetPayload(set_payload_op(ids, payload)),
+        ))
+    }
+
+    /// Replace the payload of the given points with `payload`
+    pub fn overwrite_payload(self, ids: Vec<PointIdType>, payload: Payload) -> Self {
+        self.push(CollectionUpdateOperations::PayloadOperation(
+            PayloadOps::OverwritePayload(set_payload_op(ids, payload)),
+        ))
+    }
+
+    /// Remove `keys` from the payload of the given points
+    pub fn delete_payload(self, ids: Vec<PointIdType>, keys: Vec<PayloadKeyType>) -> Self {
+        self.push(CollectionUpdateOperations::PayloadOperation(
+            PayloadOps::DeletePayload(DeletePayloadOp {
+                keys,
+                points: Some(ids),
+                filter: None,
+            }),
+        ))
+    }
+
+    /// Remove the whole payload of the given points
+    pub fn clear_payload(self, ids: Vec<PointIdType>) -> Self {
+        self.push(CollectionUpdateOperations::PayloadOperation(
+            PayloadOps::ClearPayload { points: ids },
+        ))
+    }
+
+    /// Update vectors of existing points, keeping their payload
+    pub fn update_vectors(self, points: Vec<PointVectorsPersisted>) -> Self {
+        self.push(CollectionUpdateOperations::VectorOperation(
+            VectorOperations::UpdateVectors(UpdateVectorsOp {
+                points,
+                update_filter: None,
+            }),
+        ))
+    }
+
+    /// Delete named vectors of the given points
+    pub fn delete_vectors(self, ids: Vec<PointIdType>, names: Vec<VectorNameBuf>) -> Self {
+        self.push(CollectionUpdateOperations::VectorOperation(
+            VectorOperations::DeleteVectors(PointIdsList::from(ids), names),
+        ))
+    }
+
+    /// Create a payload index on `field_name`
+    pub fn create_index(
+        self,
+        field_name: JsonPath,
+        field_schema: Option<PayloadFieldSchema>,
+    ) -> Self {
+        self.push(CollectionUpdateOperations::FieldIndexOperation(
+            FieldIndexOperations::CreateIndex(CreateIndex {
+                field_name,
+                field_schema,
+            }),
+        ))
+    }
+
+    /// Delete the payload index on `field_name`
+    pub fn delete_index(self, field_name: JsonPath) -> Self {
+        self.push(CollectionUpdateOperations::FieldIndexOperation(
+            FieldIndexOperations::DeleteIndex(field_name),
+        ))
+    }
+
+    /// Validate and return the built operations
+    ///
+    /// Fails if any operation has an empty batch, or if points of one operation provide
+    /// different sets of vectors.
+    pub fn build(self) -> Result<Vec<CollectionUpdateOperations>, UpdateBuilderError> {
+        for (index, operation) in self.operations.iter().enumerate() {
+            validate_operation(index, operation)?;
+        }
+        Ok(self.operations)
+    }
+
+    fn push(mut self, operation: CollectionUpdateOperations) -> Self {
+        self.operations.push(operation);
+        self
+    }
+}
+
+fn set_payload_op(ids: Vec<PointIdType>, payload: Payload) -> SetPayloadOp {
+    SetPayloadOp {
+        payload,
+        points: Some(ids),
+        filter: None,
+        key: None,
+    }
+}
+
+fn validate_operation(
+    index: usize,
+    operation: &CollectionUpdateOperations,
+) -> Result<(), UpdateBuilderError> {
+    let non_empty = |is_empty: bool, what| {
+        if is_empty {
+            Err(UpdateBuilderError::EmptyBatch { index, what })
+        } else {
+            Ok(())
+        }
+    };
+
+    match operation {
+        CollectionUpdateOperations::PointOperation(operation) => match operation {
+            PointOperations::UpsertPoints(PointInsertOperationsInternal::PointsList(points)) => {
+                non_empty(points.is_empty(), "points")?;
+                check_vector_names(index, points.iter().map(|point| (point.id, &point.vector)))
+            }
+            PointOperations::DeletePoints { ids } => non_empty(ids.is_empty(), "points"),
+            PointOperations::UpsertPoints(PointInsertOperationsInternal::PointsBatch(_))
+            | PointOperations::UpsertPointsConditional(_)
+            | PointOperations::DeletePointsByFilter(_)
+            | PointOperations::SyncPoints(_) => Ok(()),
+        },
+        CollectionUpdateOperations::VectorOperation(operation) => match operation {
+            VectorOperations::UpdateVectors(op) => {
+                non_empty(op.points.is_empty(), "points")?;
+                check_vector_names(
+                    index,
+                    op.points.iter().map(|point| (point.id, &point.vector)),
+                )
+            }
+            VectorOperations::DeleteVectors(points, names) => {
+                non_empty(points.points.is_empty(), "points")?;
+                non_empty(names.is_empty(), "vector names")
+            }
+            VectorOperations::DeleteVectorsByFilter(_, names) => {
+                non_empty(names.is_empty(), "vector names")
+            }
+        },
+        CollectionUpdateOperations::PayloadOperation(operation) => match operation {
+            PayloadOps::SetPayload(op) | PayloadOps::OverwritePayload(op) => {
+                non_empty(op.points.as_ref().is_some_and(Vec::is_empty), "points")
+            }
+            PayloadOps::DeletePayload(op) => {
+                non_empty(op.points.as_ref().is_some_and(Vec::is_empty), "points")?;
+                non_empty(op.keys.is_empty(), "payload keys")
+            }
+            PayloadOps::ClearPayload { points } => non_empty(points.is_empty(), "points"),
+            PayloadOps::ClearPayloadByFilter(_) => Ok(()),
+        },
+        CollectionUpdateOperations::FieldIndexOperation(_) => Ok(()),
+    }
+}
+
+/// Check that all points provide the same set of vector names
+fn check_vector_names<'a>(
+    index: usize,
+    mut points: impl Iterator<Item = (PointIdType, &'a VectorStructPersisted)>,
+) -> Result<(), UpdateBuilderError> {
+    let Some((_, first)) = points.next() else {
+        return Ok(());
+    };
+    let expected = vector_names(first);
+
+    for (point, vector) in points {
+        let names = vector_names(vector);
+        if names != expected {
+            return Err(UpdateBuilderError::InconsistentVectorNames {
+                index,
+                point,
+                expected: expected.into_iter().map(VectorNameBuf::from).collect(),
+                got: names.into_iter().map(VectorNameBuf::from).collect(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether two batches of points can be merged without mixing vector names.
+///
+/// Empty batches are never merged, so that [`UpdateBuilder::build`] rejects them.
+fn same_vector_names(points: &[PointStructPersisted], other: &[PointStructPersisted]) -> bool {
+    match (points.first(), other.first()) {
+        (Some(point), Some(other)) => vector_names(&point.vector) == vector_names(&other.vector),
+        _ => false,
+    }
+}
+
+fn vector_names(vector: &VectorStructPersisted) -> BTreeSet<&VectorName> {
+    match vector {
+        VectorStructPersisted::Single(_) | VectorStructPersisted::MultiDense(_) => {
+            BTreeSet::from([DEFAULT_VECTOR_NAME])
+        }
+        VectorStructPersisted::Named(vectors) => vectors.keys().map(String::as_str).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use segment::payload_json;
+
+    use super::*;
+    use crate::operations::point_ops::VectorPersisted;
+
+    fn named_point(id: u64, names: &[&str]) -> PointStructPersisted {
+        let vectors = names
+            .iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    VectorPersisted::Dense(vec![0.1, 0.2, 0.3]),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        PointStructPersisted {
+            id: id.into(),
+            vector: VectorStructPersisted::Named(vectors),
+            payload: Some(payload_json! {"title": format!("point {id}")}),
+        }
+    }
+
+    #[test]
+    fn test_build_operations() {
+        let operations = UpdateBuilder::new()
+            .upsert(named_point(1, &["text", "image"]))
+            .upsert(named_point(2, &["image", "text"]))
+            .set_payload(vec![1.into()], payload_json! {"color": "red"})
+            .delete(vec![3.into()])
+            .upsert(named_point(4, &["text"]))
+            .create_index(JsonPath::new("color"), None)
+            .build()
+            .unwrap();
+
+        assert_eq!(operations.len(), 5);
+        assert_eq!(
+            operations[0].point_ids(),
+            Some(vec![1.into(), 2.into()]),
+            "consecutive upserts must be merged",
+        );
+        assert!(matches!(
+            operations[1],
+            CollectionUpdateOperations::PayloadOperation(PayloadOps::SetPayload(_)),
+        ));
+        assert!(operations[2].is_delete_points());
+        assert_eq!(operations[3].point_ids(), Some(vec![4.into()]));
+        assert!(matches!(
+            operations[4],
+            CollectionUpdateOperations::FieldIndexOperation(FieldIndexOperations::CreateIndex(_)),
+        ));
+    }
+
+    #[test]
+    fn test_empty_batches_are_rejected() {
+        let err = UpdateBuilder::new()
+            .upsert(named_point(1, &["text"]))
+            .delete(vec![])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            UpdateBuilderError::EmptyBatch {
+                index: 1,
+                what: "points",
+            },
+        );
+
+        let err = UpdateBuilder::new().upsert_points([]).build().unwrap_err();
+        assert!(matches!(
+            err,
+            UpdateBuilderError::EmptyBatch { index: 0, .. }
+        ));
+
+        // Empty upserts are not merged into neighbouring upserts
+        let err = UpdateBuilder::new()
+            .upsert(named_point(1, &["text"]))
+            .upsert_points([])
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            UpdateBuilderError::EmptyBatch { index: 1, .. }
+        ));
+
+        let err = UpdateBuilder::new()
+            .upsert_points([])
+            .upsert(named_point(1, &["text"]))
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            UpdateBuilderError::EmptyBatch { index: 0, .. }
+        ));
+
+        let err = UpdateBuilder::new()
+            .delete_payload(vec![1.into()], vec![])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            UpdateBuilderError::EmptyBatch {
+                index: 0,
+                what: "payload keys",
+            },
+        );
+
+        let err = UpdateBuilder::new()
+            .delete_vectors(vec![1.into()], vec![])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            UpdateBuilderError::EmptyBatch {
+                index: 0,
+                what: "vector names",
+            },
+        );
+    }
+
+    #[test]
+    fn test_upserts_with_different_vector_names() {
+        let operations = UpdateBuilder::new()
+            .upsert(named_point(1, &["text", "image"]))
+            .upsert(named_point(2, &["text"]))
+            .upsert(named_point(3, &["text"]))
+            .build()
+            .unwrap();
+
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations[0].point_ids(), Some(vec![1.into()]));
+        assert_eq!(
+            operations[1].point_ids(),
+            Some(vec![2.into(), 3.into()]),
+            "upserts with the same vector names must be merged",
+        );
+    }
+
+    #[test]
+    fn test_inconsistent_vector_names_are_rejected() {
+        let err = UpdateBuilder::new()
+            .upsert_points([
+                named_point(1, &["text", "image"]),
+                named_point(2, &["text"]),
+            ])
+            .build()
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            UpdateBuilderError::InconsistentVectorNames {
+                index: 0,
+                point: 2.into(),
+                expected: vec!["image".to_string(), "text".to_string()],
+                got: vec!["text".to_string()],
+            },
+        );
+
+        let err = UpdateBuilder::new()
+            .update_vectors(vec![
+                PointVectorsPersisted {
+                    id: 1.into(),
+                    vector: VectorStructPersisted::Single(vec![0.1, 0.2]),
+                },
+                PointVectorsPersisted {
+                    id: 2.into(),
+                    vector: named_point(2, &["text"]).vector,
+                },
+            ])
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            UpdateBuilderError::InconsistentVectorNames { index: 0, .. },
+        ));
+    }
+
+    #[test]
+    fn test_built_operations_roundtrip() {
+        let operations = UpdateBuilder::new()
+            .upsert(named_point(1, &["text"]))
+            .set_payload(vec![1.into()], payload_json! {"color": "red"})
+            .overwrite_payload(vec![2.into()], payload_json! {"color": "blue"})
+            .delete_payload(vec![1.into()], vec![JsonPath::new("color")])
+            .clear_payload(vec![2.into()])
+            .update_vectors(vec![PointVectorsPersisted {
+                id: 1.into(),
+                vector: named_point(1, &["text"]).vector,
+            }])
+            .delete_vectors(vec![1.into()], vec!["text".to_string()])
+            .delete(vec![2.into()])
+            .delete_index(JsonPath::new("color"))
+            .build()
+            .unwrap();
+
+        let json = serde_json::to_string(&operations).unwrap();
+        let from_json: Vec<CollectionUpdateOperations> = serde_json::from_str(&json).unwrap();
+        assert_eq!(operations, from_json);
+
+        let cbor = serde_cbor::to_vec(&operations).unwrap();
+        let from_cbor: Vec<CollectionUpdateOperations> = serde_cbor::from_slice(&cbor).unwrap();
+        assert_eq!(operations, from_cbor);
+    }
+}
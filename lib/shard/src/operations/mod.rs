@@ -1,5 +1,7 @@
+pub mod builder;
 pub mod payload_ops;
 pub mod point_ops;
+pub mod sanitize;
 pub mod vector_ops;
 
 use segment::json_path::JsonPath;
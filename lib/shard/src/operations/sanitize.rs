@@ -0,0 +1,781 @@
+//! Sanity checks for operations read back from the WAL.
+//!
+//! Operations written into the WAL have been validated on the API layer, but records read back
+//! from disk bypass that validation entirely. A corrupted (or crafted) record could request huge
+//! batches, deeply nested payloads or vectors of the wrong dimensionality, and then fail deep
+//! inside segment code or, worse, be applied partially.
+//!
+//! [`sanitize_operation`] checks a deserialized operation against configured limits and the
+//! vector layout of the shard, before the operation is applied.
+//!
+//! Note that the checks run on already decoded operations. They keep oversized operations from
+//! being applied, but don't bound the memory used to decode a record.
+
+use std::cell::Cell;
+
+use api::rest::DenseVector;
+use segment::data_types::vectors::DEFAULT_VECTOR_NAME;
+use segment::types::{Payload, SegmentConfig, VectorName, VectorNameBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::CollectionUpdateOperations;
+use super::payload_ops::PayloadOps;
+use super::point_ops::{
+    BatchVectorStructPersisted, PointInsertOperationsInternal, PointOperations,
+    PointStructPersisted, VectorPersisted, VectorStructPersisted,
+};
+use super::vector_ops::VectorOperations;
+
+/// Maximum number of points a single operation may reference.
+///
+/// Far above anything a single API request can carry.
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 50_000_000;
+
+/// Maximum nesting depth of a single payload.
+///
+/// Matches the recursion limit of `serde_json`, so no payload accepted by the API exceeds it.
+pub const DEFAULT_MAX_PAYLOAD_DEPTH: usize = 128;
+
+/// Maximum approximate size of a single payload, in bytes.
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 256 * 1024 * 1024;
+
+/// Limits enforced by [`sanitize_operation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OperationLimits {
+    /// Maximum number of points a single operation may reference
+    pub max_batch_size: usize,
+    /// Maximum nesting depth of a single payload
+    pub max_payload_depth: usize,
+    /// Maximum approximate size of a single payload, in bytes
+    pub max_payload_bytes: usize,
+}
+
+impl Default for OperationLimits {
+    fn default() -> Self {
+        Self {
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            max_payload_depth: DEFAULT_MAX_PAYLOAD_DEPTH,
+            max_payload_bytes: DEFAULT_MAX_PAYLOAD_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SanitizeError {
+    #[error("operation references {size} points, limit is {limit}")]
+    BatchTooLarge { size: usize, limit: usize },
+    #[error("batch has {ids} ids, but {actual} {what}")]
+    BatchLengthMismatch {
+        ids: usize,
+        actual: usize,
+        what: &'static str,
+    },
+    #[error("payload is nested deeper than {limit} levels")]
+    PayloadTooDeep { limit: usize },
+    #[error("payload size exceeds {limit} bytes")]
+    PayloadTooLarge { limit: usize },
+    #[error("vector `{name}` is not configured in this shard")]
+    UnknownVector { name: VectorNameBuf },
+    #[error("vector `{name}` has unexpected type, expected {expected} vector")]
+    VectorTypeMismatch {
+        name: VectorNameBuf,
+        expected: &'static str,
+    },
+    #[error("vector `{name}`: expected dim {expected}, got {got}")]
+    DimensionMismatch {
+        name: VectorNameBuf,
+        expected: usize,
+        got: usize,
+    },
+    #[error("sparse vector `{name}` has {indices} indices, but {values} values")]
+    MalformedSparseVector {
+        name: VectorNameBuf,
+        indices: usize,
+        values: usize,
+    },
+    #[error("multivector `{name}` is empty")]
+    EmptyMultiVector { name: VectorNameBuf },
+}
+
+impl SanitizeError {
+    /// Whether the operation is well-formed, but references vectors which don't match the
+    /// collection config.
+    ///
+    /// The API writes such operations into the WAL before segments reject them, so they are
+    /// expected in a healthy WAL. Segments reject them on replay the same way.
+    pub fn is_vector_mismatch(&self) -> bool {
+        match self {
+            SanitizeError::UnknownVector { .. }
+            | SanitizeError::VectorTypeMismatch { .. }
+            | SanitizeError::DimensionMismatch { .. } => true,
+            SanitizeError::BatchTooLarge { .. }
+            | SanitizeError::BatchLengthMismatch { .. }
+            | SanitizeError::PayloadTooDeep { .. }
+            | SanitizeError::PayloadTooLarge { .. }
+            | SanitizeError::MalformedSparseVector { .. }
+            | SanitizeError::EmptyMultiVector { .. } => false,
+        }
+    }
+}
+
+/// Check that `operation` respects `limits` and only references vectors configured in `config`.
+///
+/// Vector mismatches (see [`SanitizeError::is_vector_mismatch`]) are only reported if the
+/// operation has no other problems.
+pub fn sanitize_operation(
+    operation: &CollectionUpdateOperations,
+    config: &SegmentConfig,
+    limits: &OperationLimits,
+) -> Result<(), SanitizeError> {
+    let sanitizer = Sanitizer {
+        config,
+        limits,
+        mismatch: Cell::new(None),
+    };
+
+    match operation {
+        CollectionUpdateOperations::PointOperation(op) => sanitizer.point_operation(op),
+        CollectionUpdateOperations::VectorOperation(op) => sanitizer.vector_operation(op),
+        CollectionUpdateOperations::PayloadOperation(op) => sanitizer.payload_operation(op),
+        CollectionUpdateOperations::FieldIndexOperation(_) => Ok(()),
+    }?;
+
+    match sanitizer.mismatch.take() {
+        Some(mismatch) => Err(mismatch),
+        None => Ok(()),
+    }
+}
+
+struct Sanitizer<'a> {
+    config: &'a SegmentConfig,
+    limits: &'a OperationLimits,
+    /// First vector mismatch found, reported after the whole operation is checked
+    mismatch: Cell<Option<SanitizeError>>,
+}
+
+impl Sanitizer<'_> {
+    fn point_operation(&self, operation: &PointOperations) -> Result<(), SanitizeError> {
+        match operation {
+            PointOperations::UpsertPoints(op) => self.insert_operation(op),
+            PointOperations::UpsertPointsConditional(op) => self.insert_operation(&op.points_op),
+            PointOperations::DeletePoints { ids } => self.batch_size(ids.len()),
+            PointOperations::DeletePointsByFilter(_) => Ok(()),
+            PointOperations::SyncPoints(op) => self.points(&op.points),
+        }
+    }
+
+    fn vector_operation(&self, operation: &VectorOperations) -> Result<(), SanitizeError> {
+        match operation {
+            VectorOperations::UpdateVectors(op) => {
+                self.batch_size(op.points.len())?;
+                op.points
+                    .iter()
+                    .try_for_each(|point| self.vector_struct(&point.vector))
+            }
+            VectorOperations::DeleteVectors(ids, names) => {
+                self.batch_size(ids.points.len())?;
+                names.iter().try_for_each(|name| self.vector_name(name))
+            }
+            VectorOperations::DeleteVectorsByFilter(_, names) => {
+                names.iter().try_for_each(|name| self.vector_name(name))
+            }
+        }
+    }
+
+    fn payload_operation(&self, operation: &PayloadOps) -> Result<(), SanitizeError> {
+        match operation {
+            PayloadOps::SetPayload(op) | PayloadOps::OverwritePayload(op) => {
+                self.batch_size(op.points.as_ref().map_or(0, Vec::len))?;
+                self.payload(&op.payload)
+            }
+            PayloadOps::DeletePayload(op) => {
+                self.batch_size(op.points.as_ref().map_or(0, Vec::len))
+            }
+            PayloadOps::ClearPayload { points } => self.batch_size(points.len()),
+            PayloadOps::ClearPayloadByFilter(_) => Ok(()),
+        }
+    }
+
+    fn insert_operation(
+        &self,
+        operation: &PointInsertOperationsInternal,
+    ) -> Result<(), SanitizeError> {
+        match operation {
+            PointInsertOperationsInternal::PointsBatch(batch) => {
+                let ids = batch.ids.len();
+                self.batch_size(ids)?;
+
+                match &batch.vectors {
+                    BatchVectorStructPersisted::Single(vectors) => {
+                        check_batch_length(ids, vectors.len(), "vectors")?;
+                        vectors
+                            .iter()
+                            .try_for_each(|vector| self.dense(DEFAULT_VECTOR_NAME, vector.len()))?;
+                    }
+                    BatchVectorStructPersisted::MultiDense(vectors) => {
+                        check_batch_length(ids, vectors.len(), "vectors")?;
+                        vectors
+                            .iter()
+                            .try_for_each(|vector| self.multi_dense(DEFAULT_VECTOR_NAME, vector))?;
+                    }
+                    BatchVectorStructPersisted::Named(named) => {
+                        for (name, vectors) in named {
+                            check_batch_length(ids, vectors.len(), "named vectors")?;
+                            vectors
+                                .iter()
+                                .try_for_each(|vector| self.vector(name, vector))?;
+                        }
+                    }
+                }
+
+                if let Some(payloads) = &batch.payloads {
+                    check_batch_length(ids, payloads.len(), "payloads")?;
+                    payloads
+                        .iter()
+                        .flatten()
+                        .try_for_each(|p| self.payload(p))?;
+                }
+
+                Ok(())
+            }
+            PointInsertOperationsInternal::PointsList(points) => self.points(points),
+        }
+    }
+
+    fn points(&self, points: &[PointStructPersisted]) -> Result<(), SanitizeError> {
+        self.batch_size(points.len())?;
+
+        for point in points {
+            self.vector_struct(&point.vector)?;
+
+            if let Some(payload) = &point.payload {
+                self.payload(payload)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn vector_struct(&self, vector: &VectorStructPersisted) -> Result<(), SanitizeError> {
+        match vector {
+            VectorStructPersisted::Single(vector) => self.dense(DEFAULT_VECTOR_NAME, vector.len()),
+            VectorStructPersisted::MultiDense(vector) => {
+                self.multi_dense(DEFAULT_VECTOR_NAME, vector)
+            }
+            VectorStructPersisted::Named(vectors) => vectors
+                .iter()
+                .try_for_each(|(name, vector)| self.vector(name, vector)),
+        }
+    }
+
+    fn vector(&self, name: &VectorName, vector: &VectorPersisted) -> Result<(), SanitizeError> {
+        match vector {
+            VectorPersisted::Dense(vector) => self.dense(name, vector.len()),
+            VectorPersisted::MultiDense(vector) => self.multi_dense(name, vector),
+            VectorPersisted::Sparse(vector) => {
+                if !self.config.sparse_vector_data.contains_key(name) {
+                    self.report_mismatch(self.missing_vector(name));
+                }
+
+                if vector.indices.len() != vector.values.len() {
+                    return Err(SanitizeError::MalformedSparseVector {
+                        name: name.to_owned(),
+                        indices: vector.indices.len(),
+                        values: vector.values.len(),
+                    });
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn dense(&self, name: &VectorName, dim: usize) -> Result<(), SanitizeError> {
+        let Some(vector_config) = self.config.vector_data.get(name) else {
+            self.report_mismatch(self.missing_vector(name));
+            return Ok(());
+        };
+
+        if vector_config.size != dim {
+            self.report_mismatch(SanitizeError::DimensionMismatch {
+                name: name.to_owned(),
+                expected: vector_config.size,
+                got: dim,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn multi_dense(&self, name: &VectorName, vectors: &[DenseVector]) -> Result<(), SanitizeError> {
+        if vectors.is_empty() {
+            return Err(SanitizeError::EmptyMultiVector {
+                name: name.to_owned(),
+            });
+        }
+
+        vectors
+            .iter()
+            .try_for_each(|vector| self.dense(name, vector.len()))
+    }
+
+    fn vector_name(&self, name: &VectorName) -> Result<(), SanitizeError> {
+        if !self.config.vector_data.contains_key(name)
+            && !self.config.sparse_vector_data.contains_key(name)
+        {
+            self.report_mismatch(SanitizeError::UnknownVector {
+                name: name.to_owned(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Remember a vector mismatch, unless an earlier one is already known.
+    fn report_mismatch(&self, mismatch: SanitizeError) {
+        let first = self.mismatch.take().unwrap_or(mismatch);
+        self.mismatch.set(Some(first));
+    }
+
+    /// Error for a vector that is not configured with the type it was given in.
+    ///
+    /// If the name is configured with another vector type, report a type mismatch instead.
+    fn missing_vector(&self, name: &VectorName) -> SanitizeError {
+        let is_dense = self.config.vector_data.contains_key(name);
+        let is_sparse = self.config.sparse_vector_data.contains_key(name);
+
+        if is_dense || is_sparse {
+            SanitizeError::VectorTypeMismatch {
+                name: name.to_owned(),
+                expected: if is_dense { "dense" } else { "sparse" },
+            }
+        } else {
+            SanitizeError::UnknownVector {
+                name: name.to_owned(),
+            }
+        }
+    }
+
+    fn batch_size(&self, size: usize) -> Result<(), SanitizeError> {
+        if size > self.limits.max_batch_size {
+            return Err(SanitizeError::BatchTooLarge {
+                size,
+                limit: self.limits.max_batch_size,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn payload(&self, payload: &Payload) -> Result<(), SanitizeError> {
+        let mut budget = self.limits.max_payload_bytes;
+
+        for (key, value) in payload.0.iter() {
+            consume_bytes(&mut budget, key.len(), self.limits)?;
+            self.value(value, 1, &mut budget)?;
+        }
+
+        Ok(())
+    }
+
+    /// Walk a payload value, checking its depth and consuming its approximate size from `budget`.
+    ///
+    /// Uses an explicit stack, so a maliciously deep value can't overflow the call stack.
+    fn value(&self, value: &Value, depth: usize, budget: &mut usize) -> Result<(), SanitizeError> {
+        let mut stack = vec![(value, depth)];
+
+        while let Some((value, depth)) = stack.pop() {
+            if depth > self.limits.max_payload_depth {
+                return Err(SanitizeError::PayloadTooDeep {
+                    limit: self.limits.max_payload_depth,
+                });
+            }
+
+            match value {
+                Value::Null | Value::Bool(_) => consume_bytes(budget, 1, self.limits)?,
+                Value::Number(_) => consume_bytes(budget, 8, self.limits)?,
+                Value::String(string) => consume_bytes(budget, string.len(), self.limits)?,
+                Value::Array(values) => {
+                    stack.extend(values.iter().map(|value| (value, depth + 1)));
+                }
+                Value::Object(map) => {
+                    for (key, value) in map {
+                        consume_bytes(budget, key.len(), self.limits)?;
+                        stack.push((value, depth + 1));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn consume_bytes(
+    budget: &mut usize,
+    bytes: usize,
+    limits: &OperationLimits,
+) -> Result<(), SanitizeError> {
+    *budget = budget
+        .checked_sub(bytes)
+        .ok_or(SanitizeError::PayloadTooLarge {
+            limit: limits.max_payload_bytes,
+        })?;
+
+    Ok(())
+}
+
+fn check_batch_length(ids: usize, actual: usize, what: &'static str) -> Result<(), SanitizeError> {
+    if ids != actual {
+        return Err(SanitizeError::BatchLengthMismatch { ids, actual, what });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use proptest::prelude::*;
+    use segment::payload_json;
+    use segment::types::{
+        Distance, Indexes, PayloadStorageType, SparseVectorDataConfig, SparseVectorStorageType,
+        VectorDataConfig, VectorStorageType,
+    };
+
+    use super::*;
+    use crate::operations::point_ops::{BatchPersisted, PointIdsList};
+    use crate::operations::vector_ops::{PointVectorsPersisted, UpdateVectorsOp};
+
+    const DIM: usize = 4;
+
+    fn config() -> SegmentConfig {
+        let dense = VectorDataConfig {
+            size: DIM,
+            distance: Distance::Dot,
+            storage_type: VectorStorageType::Memory,
+            index: Indexes::Plain {},
+            quantization_config: None,
+            multivector_config: None,
+            datatype: None,
+        };
+
+        SegmentConfig {
+            vector_data: HashMap::from([
+                (DEFAULT_VECTOR_NAME.to_owned(), dense.clone()),
+                ("text".to_owned(), dense),
+            ]),
+            sparse_vector_data: HashMap::from([(
+                "sparse".to_owned(),
+                SparseVectorDataConfig {
+                    index: Default::default(),
+                    storage_type: SparseVectorStorageType::default(),
+                },
+            )]),
+            payload_storage_type: PayloadStorageType::Mmap,
+        }
+    }
+
+    fn check(operation: CollectionUpdateOperations) -> Result<(), SanitizeError> {
+        sanitize_operation(&operation, &config(), &OperationLimits::default())
+    }
+
+    fn upsert(points: Vec<PointStructPersisted>) -> CollectionUpdateOperations {
+        CollectionUpdateOperations::PointOperation(PointOperations::UpsertPoints(
+            PointInsertOperationsInternal::PointsList(points),
+        ))
+    }
+
+    fn point(vector: VectorStructPersisted, payload: Option<Payload>) -> PointStructPersisted {
+        PointStructPersisted {
+            id: 1.into(),
+            vector,
+            payload,
+        }
+    }
+
+    #[test]
+    fn test_valid_operations() {
+        let named = VectorStructPersisted::Named(HashMap::from([
+            ("text".to_owned(), VectorPersisted::Dense(vec![0.0; DIM])),
+            (
+                "sparse".to_owned(),
+                VectorPersisted::new_sparse(vec![1, 2], vec![0.5, 0.5]),
+            ),
+        ]));
+
+        let payload = payload_json! {"a": {"b": [1, 2, "c"]}};
+
+        check(upsert(vec![
+            point(VectorStructPersisted::Single(vec![0.0; DIM]), None),
+            point(named, Some(payload)),
+        ]))
+        .unwrap();
+
+        check(CollectionUpdateOperations::VectorOperation(
+            VectorOperations::DeleteVectors(
+                PointIdsList::from(vec![1.into()]),
+                vec!["text".to_owned(), "sparse".to_owned()],
+            ),
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        let operation = CollectionUpdateOperations::VectorOperation(
+            VectorOperations::UpdateVectors(UpdateVectorsOp {
+                points: vec![PointVectorsPersisted {
+                    id: 1.into(),
+                    vector: VectorStructPersisted::Named(HashMap::from([(
+                        "text".to_owned(),
+                        VectorPersisted::Dense(vec![0.0; DIM + 1]),
+                    )])),
+                }],
+                update_filter: None,
+            }),
+        );
+
+        assert_eq!(
+            check(operation),
+            Err(SanitizeError::DimensionMismatch {
+                name: "text".to_owned(),
+                expected: DIM,
+                got: DIM + 1,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_unknown_and_mistyped_vectors() {
+        let unknown = VectorStructPersisted::Named(HashMap::from([(
+            "image".to_owned(),
+            VectorPersisted::Dense(vec![0.0; DIM]),
+        )]));
+        assert!(matches!(
+            check(upsert(vec![point(unknown, None)])),
+            Err(SanitizeError::UnknownVector { .. }),
+        ));
+
+        let mistyped = VectorStructPersisted::Named(HashMap::from([(
+            "sparse".to_owned(),
+            VectorPersisted::Dense(vec![0.0; DIM]),
+        )]));
+        assert!(matches!(
+            check(upsert(vec![point(mistyped, None)])),
+            Err(SanitizeError::VectorTypeMismatch { .. }),
+        ));
+
+        let malformed = VectorStructPersisted::Named(HashMap::from([(
+            "sparse".to_owned(),
+            VectorPersisted::new_sparse(vec![1, 2, 3], vec![0.5]),
+        )]));
+        assert!(matches!(
+            check(upsert(vec![point(malformed, None)])),
+            Err(SanitizeError::MalformedSparseVector { .. }),
+        ));
+    }
+
+    #[test]
+    fn test_malformed_vector_reported_before_mismatch() {
+        let vectors = VectorStructPersisted::Named(HashMap::from([
+            (
+                "text".to_owned(),
+                VectorPersisted::Dense(vec![0.0; DIM + 1]),
+            ),
+            (
+                "sparse".to_owned(),
+                VectorPersisted::new_sparse(vec![1, 2, 3], vec![0.5]),
+            ),
+        ]));
+
+        let result = check(upsert(vec![point(vectors, None)]));
+        assert!(matches!(
+            result,
+            Err(SanitizeError::MalformedSparseVector { .. }),
+        ));
+        assert!(!result.unwrap_err().is_vector_mismatch());
+    }
+
+    #[test]
+    fn test_batch_length_mismatch() {
+        let batch = BatchPersisted {
+            ids: vec![1.into(), 2.into(), 3.into()],
+            vectors: BatchVectorStructPersisted::Single(vec![vec![0.0; DIM]; 2]),
+            payloads: None,
+        };
+
+        let operation = CollectionUpdateOperations::PointOperation(PointOperations::UpsertPoints(
+            PointInsertOperationsInternal::PointsBatch(batch),
+        ));
+
+        assert_eq!(
+            check(operation),
+            Err(SanitizeError::BatchLengthMismatch {
+                ids: 3,
+                actual: 2,
+                what: "vectors",
+            }),
+        );
+    }
+
+    #[test]
+    fn test_payload_limits() {
+        let limits = OperationLimits {
+            max_batch_size: 10,
+            max_payload_depth: 3,
+            max_payload_bytes: 64,
+        };
+
+        let deep = payload_json! {"a": {"b": {"c": {"d": 1}}}};
+        let operation = upsert(vec![point(
+            VectorStructPersisted::Single(vec![0.0; DIM]),
+            Some(deep),
+        )]);
+        assert_eq!(
+            sanitize_operation(&operation, &config(), &limits),
+            Err(SanitizeError::PayloadTooDeep { limit: 3 }),
+        );
+
+        let large = payload_json! {"a": "x".repeat(100)};
+        let operation = upsert(vec![point(
+            VectorStructPersisted::Single(vec![0.0; DIM]),
+            Some(large),
+        )]);
+        assert_eq!(
+            sanitize_operation(&operation, &config(), &limits),
+            Err(SanitizeError::PayloadTooLarge { limit: 64 }),
+        );
+
+        let operation = CollectionUpdateOperations::PointOperation(PointOperations::DeletePoints {
+            ids: (0..11).map(Into::into).collect(),
+        });
+        assert_eq!(
+            sanitize_operation(&operation, &config(), &limits),
+            Err(SanitizeError::BatchTooLarge {
+                size: 11,
+                limit: 10
+            }),
+        );
+    }
+
+    /// Arbitrary (mostly malformed) named vectors.
+    fn any_named_vector() -> impl Strategy<Value = (VectorNameBuf, VectorPersisted)> {
+        let name = prop_oneof![
+            Just(String::new()),
+            Just("text".to_owned()),
+            Just("sparse".to_owned()),
+            "[a-z]{1,4}",
+        ];
+
+        let vector = prop_oneof![
+            prop::collection::vec(any::<f32>(), 0..8).prop_map(VectorPersisted::Dense),
+            prop::collection::vec(prop::collection::vec(any::<f32>(), 0..8), 0..4)
+                .prop_map(VectorPersisted::MultiDense),
+            (
+                prop::collection::vec(any::<u32>(), 0..8),
+                prop::collection::vec(any::<f32>(), 0..8),
+            )
+                .prop_map(|(indices, values)| VectorPersisted::new_sparse(indices, values)),
+        ];
+
+        (name, vector)
+    }
+
+    proptest! {
+        /// Sanitizing arbitrary operations must never panic, and accepted operations must only
+        /// reference configured vectors with matching dimensions.
+        #[test]
+        fn sanitize_arbitrary_points(
+            vectors in prop::collection::vec(prop::collection::vec(any_named_vector(), 0..4), 0..8),
+        ) {
+            let config = config();
+
+            let points: Vec<_> = vectors
+                .into_iter()
+                .map(|vectors| point(VectorStructPersisted::Named(HashMap::from_iter(vectors)), None))
+                .collect();
+
+            let result = sanitize_operation(&upsert(points.clone()), &config, &OperationLimits::default());
+
+            let vectors = points.iter().flat_map(|point| match &point.vector {
+                VectorStructPersisted::Named(vectors) => vectors.iter(),
+                _ => unreachable!(),
+            });
+
+            match result {
+                Ok(()) => (),
+                // A mismatch is only reported for otherwise well-formed operations
+                Err(err) if err.is_vector_mismatch() => {
+                    for (_, vector) in vectors {
+                        match vector {
+                            VectorPersisted::Dense(_) => (),
+                            VectorPersisted::MultiDense(vectors) => prop_assert!(!vectors.is_empty()),
+                            VectorPersisted::Sparse(vector) => {
+                                prop_assert_eq!(vector.indices.len(), vector.values.len());
+                            }
+                        }
+                    }
+                    return Ok(());
+                }
+                Err(_) => return Ok(()),
+            }
+
+            for (name, vector) in vectors {
+                match vector {
+                    VectorPersisted::Dense(vector) => {
+                        prop_assert_eq!(config.vector_data[name].size, vector.len());
+                    }
+                    VectorPersisted::MultiDense(vectors) => {
+                        prop_assert!(!vectors.is_empty());
+                        for vector in vectors {
+                            prop_assert_eq!(config.vector_data[name].size, vector.len());
+                        }
+                    }
+                    VectorPersisted::Sparse(vector) => {
+                        prop_assert!(config.sparse_vector_data.contains_key(name));
+                        prop_assert_eq!(vector.indices.len(), vector.values.len());
+                    }
+                }
+            }
+        }
+
+        /// Batches with inconsistent lengths must be rejected, consistent ones must convert into
+        /// points without panicking.
+        #[test]
+        fn sanitize_arbitrary_batches(
+            vectors in prop::collection::vec((any_named_vector(), 0..8usize), 0..4),
+            ids in prop::collection::vec(any::<u64>(), 0..8),
+        ) {
+            let named: HashMap<_, _> = vectors
+                .into_iter()
+                .map(|((name, vector), count)| (name, vec![vector; count]))
+                .collect();
+
+            let lengths_match = named.values().all(|vectors| vectors.len() == ids.len());
+
+            let batch = BatchPersisted {
+                ids: ids.into_iter().map(Into::into).collect(),
+                vectors: BatchVectorStructPersisted::Named(named),
+                payloads: None,
+            };
+
+            let operation = PointInsertOperationsInternal::PointsBatch(batch);
+            let result = check(CollectionUpdateOperations::PointOperation(
+                PointOperations::UpsertPoints(operation.clone()),
+            ));
+
+            if !lengths_match {
+                prop_assert!(result.is_err());
+            }
+
+            if result.is_ok() {
+                operation.into_point_vec();
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::marker::PhantomData;
 use std::path::Path;
 use std::result;
@@ -21,11 +22,53 @@ pub struct SerdeWal<R> {
     options: WalOptions,
     /// First index of our logical WAL.
     first_index: Option<u64>,
+    /// Compression applied to newly written records.
+    compression: WalCompression,
     _record: PhantomData<R>,
 }
 
 const FIRST_INDEX_FILE: &str = "first-index";
 
+/// First byte of a compressed record, followed by a [`CompressionAlgorithm`] byte.
+///
+/// Stored records are structs or enums, which CBOR and MessagePack encode as maps or arrays. Their
+/// encoding never starts with `0xFF`, so compressed and plain records can be told apart and
+/// coexist in one WAL.
+const COMPRESSED_RECORD_MARKER: u8 = 0xFF;
+
+/// Compression of records written into the WAL.
+///
+/// Only affects new records, records of any kind can always be read back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WalCompression {
+    #[default]
+    None,
+    /// Compress records of at least `min_record_size` bytes with LZ4.
+    ///
+    /// A record is stored plain if compression doesn't make it smaller.
+    Lz4 { min_record_size: usize },
+}
+
+/// LZ4 can't expand a single input byte into more than 255 output bytes.
+///
+/// Decompressed sizes above this ratio can only come from a corrupted record.
+const LZ4_MAX_COMPRESSION_RATIO: usize = 255;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompressionAlgorithm {
+    Lz4 = 1,
+}
+
+impl CompressionAlgorithm {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+}
+
 impl<R: DeserializeOwned + Serialize> SerdeWal<R> {
     pub fn new(dir: &str, wal_options: WalOptions) -> Result<SerdeWal<R>> {
         let wal = Wal::with_options(dir, &wal_options)
@@ -51,14 +94,26 @@ impl<R: DeserializeOwned + Serialize> SerdeWal<R> {
             wal,
             options: wal_options,
             first_index,
+            compression: WalCompression::default(),
             _record: PhantomData,
         })
     }
 
+    /// Set compression of newly written records.
+    pub fn with_compression(mut self, compression: WalCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn compression(&self) -> WalCompression {
+        self.compression
+    }
+
     /// Write a record to the WAL but does guarantee durability.
     pub fn write(&mut self, entity: &R) -> Result<u64> {
         // ToDo: Replace back to faster rmp, once this https://github.com/serde-rs/serde/issues/2055 solved
         let binary_entity = serde_cbor::to_vec(&entity).unwrap();
+        let binary_entity = compress_record(&binary_entity, self.compression);
         self.wal
             .append(&binary_entity)
             .map_err(|err| WalError::WriteWalError(format!("{err:?}")))
@@ -76,21 +131,48 @@ impl<R: DeserializeOwned + Serialize> SerdeWal<R> {
     }
 
     pub fn read(&self, from: u64) -> impl DoubleEndedIterator<Item = (u64, R)> + '_ {
+        self.try_read(from).map(|(idx, record)| {
+            let record =
+                record.expect("Can't read entry, probably corrupted WAL or version mismatch");
+            (idx, record)
+        })
+    }
+
+    /// Same as [`SerdeWal::read_all`], but yields an error for records which can't be read back,
+    /// instead of panicking.
+    pub fn try_read_all(
+        &self,
+        with_acknowledged: bool,
+    ) -> impl DoubleEndedIterator<Item = (u64, Result<R>)> + '_ {
+        if with_acknowledged {
+            self.try_read(self.first_closed_index())
+        } else {
+            self.try_read(self.first_index())
+        }
+    }
+
+    /// Same as [`SerdeWal::read`], but yields an error for records which can't be read back,
+    /// instead of panicking.
+    pub fn try_read(&self, from: u64) -> impl DoubleEndedIterator<Item = (u64, Result<R>)> + '_ {
         // We have to explicitly do `from..self.first_index() + self.len(false)`, instead of more
         // concise `from..=self.last_index()`, because if the WAL is empty, `Wal::last_index`
         // returns `Wal::first_index`, so we end up with `1..=1` instead of an empty range. 😕
 
         let to = self.first_index() + self.len(false);
 
-        (from..to).map(move |idx| {
-            let record_bin = self.wal.entry(idx).expect("Can't read entry from WAL");
+        (from..to).map(move |idx| (idx, self.read_record(idx)))
+    }
 
-            let record: R = serde_cbor::from_slice(&record_bin)
-                .or_else(|_err| rmp_serde::from_slice(&record_bin))
-                .expect("Can't deserialize entry, probably corrupted WAL or version mismatch");
+    fn read_record(&self, idx: u64) -> Result<R> {
+        let record_bin = self
+            .wal
+            .entry(idx)
+            .ok_or_else(|| WalError::ReadWalError(format!("entry {idx} is missing")))?;
+        let record_bin = decompress_record(&record_bin)?;
 
-            (idx, record)
-        })
+        serde_cbor::from_slice(&record_bin)
+            .or_else(|_err| rmp_serde::from_slice(&record_bin))
+            .map_err(|err| WalError::DecodeWalError(format!("entry {idx}: {err}")))
     }
 
     pub fn is_empty(&self) -> bool {
@@ -212,6 +294,67 @@ impl<R: DeserializeOwned + Serialize> SerdeWal<R> {
     }
 }
 
+/// Compress a serialized record according to `compression`, prepending the record header.
+fn compress_record(record: &[u8], compression: WalCompression) -> Cow<'_, [u8]> {
+    match compression {
+        WalCompression::None => Cow::Borrowed(record),
+        WalCompression::Lz4 { min_record_size } => {
+            if record.len() < min_record_size {
+                return Cow::Borrowed(record);
+            }
+
+            let compressed = lz4_flex::compress_prepend_size(record);
+            if compressed.len() + 2 >= record.len() {
+                return Cow::Borrowed(record);
+            }
+
+            let mut buffer = Vec::with_capacity(compressed.len() + 2);
+            buffer.push(COMPRESSED_RECORD_MARKER);
+            buffer.push(CompressionAlgorithm::Lz4 as u8);
+            buffer.extend_from_slice(&compressed);
+            Cow::Owned(buffer)
+        }
+    }
+}
+
+/// Strip the header of a compressed record and decompress it. Plain records are returned as is.
+fn decompress_record(record: &[u8]) -> Result<Cow<'_, [u8]>> {
+    let [COMPRESSED_RECORD_MARKER, algorithm, ..] = record else {
+        return Ok(Cow::Borrowed(record));
+    };
+
+    match CompressionAlgorithm::from_u8(*algorithm) {
+        Some(CompressionAlgorithm::Lz4) => decompress_lz4(&record[2..]).map(Cow::Owned),
+        None => Err(WalError::DecodeWalError(format!(
+            "record is compressed with unknown algorithm {algorithm}"
+        ))),
+    }
+}
+
+/// Decompress LZ4 data with a prepended little-endian `u32` size.
+///
+/// The size is checked against the compressed length before allocating, so a corrupted size
+/// can't trigger an arbitrarily large allocation.
+fn decompress_lz4(data: &[u8]) -> Result<Vec<u8>> {
+    let Some((size, compressed)) = data.split_first_chunk::<4>() else {
+        return Err(WalError::DecodeWalError(
+            "compressed record is truncated".to_string(),
+        ));
+    };
+
+    let size = u32::from_le_bytes(*size) as usize;
+    let max_size = compressed.len().saturating_mul(LZ4_MAX_COMPRESSION_RATIO);
+    if size > max_size {
+        return Err(WalError::DecodeWalError(format!(
+            "compressed record of {} bytes claims to decompress into {size} bytes",
+            compressed.len(),
+        )));
+    }
+
+    lz4_flex::decompress(compressed, size)
+        .map_err(|err| WalError::DecodeWalError(format!("can't decompress record: {err}")))
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct WalState {
     pub ack_index: u64,
@@ -232,6 +375,10 @@ pub enum WalError {
     InitWalError(String),
     #[error("Can't write WAL: {0}")]
     WriteWalError(String),
+    #[error("Can't read WAL: {0}")]
+    ReadWalError(String),
+    #[error("Can't decode WAL record: {0}")]
+    DecodeWalError(String),
     #[error("Can't truncate WAL: {0}")]
     TruncateWalError(String),
     #[error("Operation rejected by WAL for old clock")]
@@ -246,9 +393,17 @@ mod tests {
     #[cfg(not(target_os = "windows"))]
     use std::os::unix::fs::MetadataExt;
 
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use segment::payload_json;
     use tempfile::Builder;
 
     use super::*;
+    use crate::operations::CollectionUpdateOperations;
+    use crate::operations::point_ops::{
+        PointInsertOperationsInternal, PointOperations, PointStructPersisted, VectorStructPersisted,
+    };
 
     #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "snake_case")]
@@ -324,4 +479,210 @@ mod tests {
             }
         }
     }
+
+    fn test_wal_options() -> WalOptions {
+        WalOptions {
+            segment_capacity: 32 * 1024 * 1024,
+            segment_queue_len: 0,
+            retain_closed: NonZeroUsize::new(1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_wal_corrupted_record() {
+        let dir = Builder::new().prefix("wal_test").tempdir().unwrap();
+        let mut serde_wal: SerdeWal<TestRecord> =
+            SerdeWal::new(dir.path().to_str().unwrap(), test_wal_options()).unwrap();
+
+        serde_wal
+            .write(&TestRecord::Struct1(TestInternalStruct1 { data: 10 }))
+            .unwrap();
+        serde_wal.wal.append(&b"not a record".to_vec()).unwrap();
+        serde_wal
+            .write(&TestRecord::Struct2(TestInternalStruct2 { a: 12, b: 13 }))
+            .unwrap();
+
+        let records: Vec<_> = serde_wal.try_read_all(false).collect();
+        assert_eq!(records.len(), 3);
+        assert!(matches!(records[0], (0, Ok(TestRecord::Struct1(_)))));
+        assert!(matches!(records[1], (1, Err(WalError::DecodeWalError(_)))));
+        assert!(matches!(records[2], (2, Ok(TestRecord::Struct2(_)))));
+    }
+
+    proptest! {
+        #[test]
+        fn test_wal_arbitrary_record(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+            let dir = Builder::new().prefix("wal_test").tempdir().unwrap();
+            let mut serde_wal: SerdeWal<TestRecord> =
+                SerdeWal::new(dir.path().to_str().unwrap(), test_wal_options()).unwrap();
+            serde_wal.wal.append(&bytes).unwrap();
+
+            // Arbitrary bytes must never panic, whether or not they happen to decode
+            let records: Vec<_> = serde_wal.try_read_all(false).collect();
+            prop_assert_eq!(records.len(), 1);
+        }
+
+        #[test]
+        fn test_wal_arbitrary_lz4_record(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+            let mut record = vec![COMPRESSED_RECORD_MARKER, CompressionAlgorithm::Lz4 as u8];
+            record.extend_from_slice(&bytes);
+
+            // Arbitrary compressed data must never panic or allocate more than it can expand to
+            if let Ok(decompressed) = decompress_record(&record) {
+                prop_assert!(decompressed.len() <= bytes.len() * LZ4_MAX_COMPRESSION_RATIO);
+            }
+        }
+    }
+
+    /// An upsert of a batch of points with small vectors and text-heavy payloads.
+    fn upsert_batch(seed: u64) -> CollectionUpdateOperations {
+        const WORDS: &[&str] = &[
+            "storage",
+            "vector",
+            "search",
+            "engine",
+            "payload",
+            "filter",
+            "index",
+            "segment",
+            "cluster",
+            "replica",
+            "snapshot",
+            "collection",
+            "quantization",
+            "distance",
+            "query",
+            "shard",
+        ];
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let points = (0..100)
+            .map(|i| {
+                let id = seed * 1000 + i;
+                let description = (0..40)
+                    .map(|_| WORDS[rng.random_range(0..WORDS.len())])
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                PointStructPersisted {
+                    id: id.into(),
+                    vector: VectorStructPersisted::Single(
+                        (0..16).map(|_| rng.random_range(-1.0..1.0)).collect(),
+                    ),
+                    payload: Some(payload_json! {
+                        "title": format!("document {id}"),
+                        "category": WORDS[rng.random_range(0..WORDS.len())],
+                        "price": rng.random_range(1..10_000),
+                        "description": description,
+                    }),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        CollectionUpdateOperations::PointOperation(PointOperations::UpsertPoints(
+            PointInsertOperationsInternal::from(points),
+        ))
+    }
+
+    #[test]
+    fn test_wal_mixed_compression() {
+        let dir = Builder::new().prefix("wal_test").tempdir().unwrap();
+        let wal_options = || WalOptions {
+            segment_capacity: 32 * 1024 * 1024,
+            segment_queue_len: 0,
+            retain_closed: NonZeroUsize::new(1).unwrap(),
+        };
+
+        let mut serde_wal: SerdeWal<CollectionUpdateOperations> =
+            SerdeWal::new(dir.path().to_str().unwrap(), wal_options()).unwrap();
+        serde_wal.write(&upsert_batch(0)).unwrap();
+        serde_wal.write(&upsert_batch(1)).unwrap();
+        serde_wal.flush().unwrap();
+        drop(serde_wal);
+
+        // Reopen the WAL with compression enabled, old records must remain readable
+        let mut serde_wal: SerdeWal<CollectionUpdateOperations> =
+            SerdeWal::new(dir.path().to_str().unwrap(), wal_options())
+                .unwrap()
+                .with_compression(WalCompression::Lz4 { min_record_size: 0 });
+        serde_wal.write(&upsert_batch(2)).unwrap();
+        serde_wal.write(&upsert_batch(3)).unwrap();
+
+        for idx in 0..4 {
+            let stored_size = serde_wal.wal.entry(idx).unwrap().len();
+            let plain_size = serde_cbor::to_vec(&upsert_batch(idx)).unwrap().len();
+            if idx < 2 {
+                assert_eq!(stored_size, plain_size);
+            } else {
+                assert!(stored_size < plain_size);
+            }
+        }
+
+        let records: Vec<_> = serde_wal.read(0).collect();
+        assert_eq!(records.len(), 4);
+        for (idx, record) in records {
+            assert_eq!(record, upsert_batch(idx));
+        }
+    }
+
+    #[test]
+    fn test_wal_compression_threshold() {
+        let record = serde_cbor::to_vec(&upsert_batch(0)).unwrap();
+
+        // Records below the threshold are stored plain
+        let compression = WalCompression::Lz4 {
+            min_record_size: record.len() + 1,
+        };
+        assert!(matches!(
+            compress_record(&record, compression),
+            Cow::Borrowed(_)
+        ));
+
+        let compression = WalCompression::Lz4 {
+            min_record_size: record.len(),
+        };
+        let compressed = compress_record(&record, compression);
+        assert_eq!(compressed[0], COMPRESSED_RECORD_MARKER);
+        assert_eq!(decompress_record(&compressed).unwrap(), record.as_slice());
+
+        // Incompressible records are stored plain
+        let record = serde_cbor::to_vec(&TestInternalStruct1 { data: 10 }).unwrap();
+        assert!(matches!(
+            compress_record(&record, compression),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_wal_corrupted_compressed_size() {
+        let dir = Builder::new().prefix("wal_test").tempdir().unwrap();
+        let mut serde_wal: SerdeWal<CollectionUpdateOperations> =
+            SerdeWal::new(dir.path().to_str().unwrap(), test_wal_options())
+                .unwrap()
+                .with_compression(WalCompression::Lz4 { min_record_size: 0 });
+        serde_wal.write(&upsert_batch(0)).unwrap();
+
+        let record = serde_cbor::to_vec(&upsert_batch(1)).unwrap();
+        let mut compressed = compress_record(&record, serde_wal.compression()).into_owned();
+        assert_eq!(compressed[0], COMPRESSED_RECORD_MARKER);
+
+        // Huge decompressed size
+        compressed[2..6].copy_from_slice(&u32::MAX.to_le_bytes());
+        serde_wal.wal.append(&compressed).unwrap();
+
+        // Truncated size
+        serde_wal
+            .wal
+            .append(&[COMPRESSED_RECORD_MARKER, CompressionAlgorithm::Lz4 as u8, 0])
+            .unwrap();
+
+        serde_wal.write(&upsert_batch(3)).unwrap();
+
+        let records: Vec<_> = serde_wal.try_read_all(false).collect();
+        assert_eq!(records.len(), 4);
+        assert!(matches!(records[0], (0, Ok(_))));
+        assert!(matches!(records[1], (1, Err(WalError::DecodeWalError(_)))));
+        assert!(matches!(records[2], (2, Err(WalError::DecodeWalError(_)))));
+        assert!(matches!(records[3], (3, Ok(_))));
+    }
 }
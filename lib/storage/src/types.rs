@@ -6,6 +6,7 @@ use chrono::{DateTime, Utc};
 use collection::common::snapshots_manager::SnapshotsConfig;
 use collection::config::{WalConfig, default_on_disk_payload};
 use collection::operations::config_diff::OptimizersConfigDiff;
+use collection::operations::sanitize::OperationLimits;
 use collection::operations::shared_storage_config::{
     DEFAULT_IO_SHARD_TRANSFER_LIMIT, DEFAULT_SNAPSHOTS_PATH, SharedStorageConfig,
 };
@@ -94,6 +95,15 @@ pub struct StorageConfig {
     pub update_queue_size: Option<usize>,
     #[serde(default)]
     pub handle_collection_load_errors: bool,
+    /// If true - WAL records which fail sanity checks on load (e.g. malformed vectors, or
+    /// oversized batches) are skipped with a warning.
+    /// Otherwise the shard fails to load.
+    #[serde(default)]
+    pub skip_invalid_wal_records: bool,
+    /// Limits for operations read back from WAL on load. Operations exceeding them are treated
+    /// as invalid records, see `skip_invalid_wal_records`.
+    #[serde(default)]
+    pub wal_operation_limits: OperationLimits,
     /// If provided - qdrant will start in recovery mode, which means that it will not accept any new data.
     /// Only collection metadata will be available, and it will only process collection delete requests.
     /// Provided value will be used error message for unavailable requests.
@@ -119,6 +129,8 @@ impl StorageConfig {
             self.update_queue_size,
             self.node_type,
             self.handle_collection_load_errors,
+            self.skip_invalid_wal_records,
+            self.wal_operation_limits,
             self.recovery_mode.clone(),
             self.performance
                 .search_timeout_sec
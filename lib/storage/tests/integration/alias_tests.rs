@@ -67,6 +67,8 @@ fn test_alias_operation() {
         node_type: Default::default(),
         update_queue_size: Default::default(),
         handle_collection_load_errors: false,
+        skip_invalid_wal_records: false,
+        wal_operation_limits: Default::default(),
         recovery_mode: None,
         update_concurrency: Some(NonZeroUsize::new(2).unwrap()),
         // update_concurrency: None,
@@ -66,12 +66,15 @@ fn print_collection_wal(wal_path: &Path) {
         Ok(wal) => {
             // print all entries
             let mut count = 0;
-            for (idx, op) in wal.read_all(true) {
+            for (idx, op) in wal.try_read_all(true) {
                 println!("==========================");
-                println!(
-                    "Entry: {idx} Operation: {:?} Clock: {:?}",
-                    op.operation, op.clock_tag
-                );
+                match op {
+                    Ok(op) => println!(
+                        "Entry: {idx} Operation: {:?} Clock: {:?}",
+                        op.operation, op.clock_tag
+                    ),
+                    Err(error) => println!("Entry: {idx} Error: {error}"),
+                }
                 count += 1;
             }
             println!("==========================");